@@ -1,101 +1,358 @@
 pub(crate) use ::{
     serde::{
-        de::{self, Deserialize, Deserializer, Visitor},
-        ser::{Serialize, Serializer},
+        de::{self, Deserialize, Deserializer},
+        ser::{self, Serialize, Serializer},
     },
     std::{
         fmt::{self, Formatter},
         fs::{File, OpenOptions},
         io::{self, prelude::*, SeekFrom},
-        marker::PhantomData,
-        mem,
         ops::{Deref, DerefMut},
         path::{Path, PathBuf},
-        slice,
     },
 };
 
-fn bytes<T: ?Sized>(x: &T) -> &[u8] {
-    unsafe { slice::from_raw_parts(x as *const _ as *const u8, mem::size_of_val(x)) }
+#[cfg(feature = "scm-rights")]
+mod scm_rights;
+
+#[cfg(feature = "scm-rights")]
+pub use scm_rights::{recv, send};
+
+/// Portable, serializable description of the flags a [`File`] was opened with.
+///
+/// [`OpenOptions`] exposes no getters and its in-memory layout is unspecified and differs
+/// across platforms (and even std versions), so it cannot be serialized directly. `OpenMode`
+/// mirrors the same flags as a plain struct of named fields, which serde can (de)serialize
+/// portably, and knows how to turn itself back into a fresh [`OpenOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpenMode {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+    pub create_new: bool,
+    pub mode: Option<u32>,
+    pub custom_flags: Option<i32>,
 }
 
-struct BytesSer<T>(pub T);
+/// The named-field shape `OpenMode` serializes as in human-readable formats, and unconditionally
+/// when the `compact` feature is off. Kept separate so `OpenMode`'s own `Serialize`/
+/// `Deserialize` impls can route non-human-readable formats through a bit-packed encoding instead
+/// (a size optimization, not a format-compatibility fix — `OpenMode` has serialized as named
+/// fields, which are JSON/YAML-safe, since it stopped being raw [`OpenOptions`] bytes). Either way
+/// [`SerdeFile`]'s own (de)serialization only ever depends on `OpenMode: Serialize + Deserialize`
+/// and is oblivious to which shape is chosen underneath.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct OpenModeFields {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    mode: Option<u32>,
+    custom_flags: Option<i32>,
+}
 
-impl<T: fmt::Debug> fmt::Debug for BytesSer<T> {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        self.0.fmt(f)
+impl From<OpenMode> for OpenModeFields {
+    fn from(
+        OpenMode {
+            read,
+            write,
+            append,
+            truncate,
+            create,
+            create_new,
+            mode,
+            custom_flags,
+        }: OpenMode,
+    ) -> Self {
+        OpenModeFields {
+            read,
+            write,
+            append,
+            truncate,
+            create,
+            create_new,
+            mode,
+            custom_flags,
+        }
+    }
+}
+
+impl From<OpenModeFields> for OpenMode {
+    fn from(
+        OpenModeFields {
+            read,
+            write,
+            append,
+            truncate,
+            create,
+            create_new,
+            mode,
+            custom_flags,
+        }: OpenModeFields,
+    ) -> Self {
+        OpenMode {
+            read,
+            write,
+            append,
+            truncate,
+            create,
+            create_new,
+            mode,
+            custom_flags,
+        }
     }
 }
 
-impl<T> Serialize for BytesSer<T> {
+#[cfg(feature = "compact")]
+const OPEN_MODE_READ: u8 = 1 << 0;
+#[cfg(feature = "compact")]
+const OPEN_MODE_WRITE: u8 = 1 << 1;
+#[cfg(feature = "compact")]
+const OPEN_MODE_APPEND: u8 = 1 << 2;
+#[cfg(feature = "compact")]
+const OPEN_MODE_TRUNCATE: u8 = 1 << 3;
+#[cfg(feature = "compact")]
+const OPEN_MODE_CREATE: u8 = 1 << 4;
+#[cfg(feature = "compact")]
+const OPEN_MODE_CREATE_NEW: u8 = 1 << 5;
+
+impl Serialize for OpenMode {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_bytes(bytes(self))
+        #[cfg(feature = "compact")]
+        if !serializer.is_human_readable() {
+            let mut flags = 0u8;
+
+            for (set, bit) in [
+                (self.read, OPEN_MODE_READ),
+                (self.write, OPEN_MODE_WRITE),
+                (self.append, OPEN_MODE_APPEND),
+                (self.truncate, OPEN_MODE_TRUNCATE),
+                (self.create, OPEN_MODE_CREATE),
+                (self.create_new, OPEN_MODE_CREATE_NEW),
+            ] {
+                if set {
+                    flags |= bit;
+                }
+            }
+
+            return (flags, self.mode, self.custom_flags).serialize(serializer);
+        }
+
+        OpenModeFields::from(*self).serialize(serializer)
     }
 }
 
-struct BytesVisitor<T>(PhantomData<T>);
+impl<'de> Deserialize<'de> for OpenMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[cfg(feature = "compact")]
+        if !deserializer.is_human_readable() {
+            let (flags, mode, custom_flags): (u8, Option<u32>, Option<i32>) =
+                Deserialize::deserialize(deserializer)?;
+
+            return Ok(OpenMode {
+                read: flags & OPEN_MODE_READ != 0,
+                write: flags & OPEN_MODE_WRITE != 0,
+                append: flags & OPEN_MODE_APPEND != 0,
+                truncate: flags & OPEN_MODE_TRUNCATE != 0,
+                create: flags & OPEN_MODE_CREATE != 0,
+                create_new: flags & OPEN_MODE_CREATE_NEW != 0,
+                mode,
+                custom_flags,
+            });
+        }
 
-impl<'a, T: 'a> Visitor<'a> for BytesVisitor<T> {
-    type Value = BytesSer<T>;
+        OpenModeFields::deserialize(deserializer).map(Into::into)
+    }
+}
 
-    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "expecting a byte buffer")
+impl OpenMode {
+    /// Creates a new `OpenMode` with every flag unset, mirroring [`OpenOptions::new`].
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
-        Ok(unsafe { bytes.as_ptr().cast::<Self::Value>().read() })
+    #[inline]
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    #[inline]
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    #[inline]
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    #[inline]
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
     }
+
+    #[inline]
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Sets the Unix `mode` bits to use when creating the file, mirroring
+    /// `OpenOptionsExt::mode`. A no-op on non-Unix targets.
+    #[inline]
+    pub fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Sets the Unix custom open flags, mirroring `OpenOptionsExt::custom_flags`. A no-op on
+    /// non-Unix targets.
+    #[inline]
+    pub fn custom_flags(&mut self, custom_flags: i32) -> &mut Self {
+        self.custom_flags = Some(custom_flags);
+        self
+    }
+
+    /// Builds a fresh [`OpenOptions`] configured with these flags.
+    pub fn to_open_options(&self) -> OpenOptions {
+        let mut options = OpenOptions::new();
+
+        options
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            if let Some(mode) = self.mode {
+                options.mode(mode);
+            }
+
+            if let Some(custom_flags) = self.custom_flags {
+                options.custom_flags(custom_flags);
+            }
+        }
+
+        options
+    }
+}
+
+/// A pluggable storage backend a [`SerdeFile`] can be generic over, so handles opened against
+/// virtual or in-memory filesystems (sandboxes, WASM hosts, overlay filesystems, ...) can be
+/// serialized the same way a plain [`File`] can. With the `scm-rights` feature enabled,
+/// `SerdeFile`'s (de)serialization also requires the backend's `File` to implement `AsRawFd`/
+/// `FromRawFd`, so backends without real descriptors cannot be (de)serialized then.
+pub trait FileSystem {
+    /// The open handle this backend produces, analogous to [`File`].
+    type File: Read + Write + Seek;
+
+    /// The portable description of the flags a handle was opened with, analogous to
+    /// [`OpenMode`].
+    type OpenOptions: Clone;
+
+    /// Opens a handle at `path` configured with `options`, analogous to [`OpenMode::to_open_options`]
+    /// followed by [`OpenOptions::open`].
+    fn open(options: &Self::OpenOptions, path: &Path) -> io::Result<Self::File>;
+
+    /// Canonicalizes `path` against this backend, analogous to [`Path::canonicalize`].
+    fn canonicalize(path: &Path) -> io::Result<PathBuf>;
 }
 
-impl<'a, T: 'a> Deserialize<'a> for BytesSer<T> {
-    fn deserialize<D: Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
-        d.deserialize_bytes(BytesVisitor(PhantomData))
+/// The default [`FileSystem`], backed by [`std::fs`] and preserving `SerdeFile`'s original,
+/// pre-[`FileSystem`] behavior.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFs;
+
+impl FileSystem for StdFs {
+    type File = File;
+    type OpenOptions = OpenMode;
+
+    #[inline]
+    fn open(options: &OpenMode, path: &Path) -> io::Result<File> {
+        options.to_open_options().open(path)
+    }
+
+    #[inline]
+    fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
     }
 }
 
-/// Little wrapper over an [`OpenOptions`],a [`File`] and it's path with the purporse of
+/// Little wrapper over an `FS`'s open options,its file handle and it's path with the purporse of
 /// implementing [`Serialize`] and [`Deserialize`],this wrapper implements exactly the same traits
-/// as the [`File`] in the way it does and also [derefs][`Deref`] to it.
-#[derive(Debug)]
-// todo: remove the unsafe BytesSer wrapper once OpenOptions it's supported in serde
-pub struct SerdeFile(BytesSer<OpenOptions>, File, PathBuf);
-
-impl SerdeFile {
-    /// Creates a new `Self` opening a [`File`] with [`OpenOptions::open`] on `x` and
-    /// the path `path` [`canonicalize`]d.
-    pub fn open<P: AsRef<Path>>(x: &OpenOptions, path: P) -> io::Result<Self> {
+/// as the inner file in the way it does and also [derefs][`Deref`] to it. Generic over the
+/// storage [`FileSystem`] so it defaults to plain [`std::fs`] files but also works against
+/// virtual/in-memory backends.
+pub struct SerdeFile<FS: FileSystem = StdFs>(FS::OpenOptions, FS::File, PathBuf);
+
+impl<FS: FileSystem> fmt::Debug for SerdeFile<FS>
+where
+    FS::OpenOptions: fmt::Debug,
+    FS::File: fmt::Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("SerdeFile")
+            .field(&self.0)
+            .field(&self.1)
+            .field(&self.2)
+            .finish()
+    }
+}
+
+impl<FS: FileSystem> SerdeFile<FS> {
+    /// Creates a new `Self` opening a handle with [`FS::open`][FileSystem::open] on `x` and
+    /// the path `path` [canonicalize][FileSystem::canonicalize]d.
+    pub fn open<P: AsRef<Path>>(x: &FS::OpenOptions, path: P) -> io::Result<Self> {
         // technique copied from the std to being able to inline a function that have generics
         // just make a function that does not have it and inline it
         #[inline]
-        fn a(x: &OpenOptions, path: &Path) -> io::Result<SerdeFile> {
-            x.open(path).and_then(|file| {
-                Ok(SerdeFile(BytesSer(x.clone()), file, path.canonicalize()?))
-            })
+        fn a<FS: FileSystem>(x: &FS::OpenOptions, path: &Path) -> io::Result<SerdeFile<FS>> {
+            FS::open(x, path)
+                .and_then(|file| Ok(SerdeFile(x.clone(), file, FS::canonicalize(path)?)))
         }
 
-        a(x, path.as_ref())
+        a::<FS>(x, path.as_ref())
     }
 
-    /// Returns a reference to the canonicalized path to the inner `File`.
+    /// Returns a reference to the canonicalized path to the inner file.
     #[inline]
     pub fn path(&self) -> &Path {
         &self.2
     }
-    
-    /// Returns a reference to the `OpenOptions` used to open the inner `File`.
+
+    /// Returns a reference to the options the inner file was opened with.
     #[inline]
-    pub fn options(&self) -> &OpenOptions {
-        &self.0.0
+    pub fn options(&self) -> &FS::OpenOptions {
+        &self.0
     }
 
-    pub fn into_inner(self) -> (OpenOptions, File, PathBuf) {
+    pub fn into_inner(self) -> (FS::OpenOptions, FS::File, PathBuf) {
         let Self(options, file, path_buf) = self;
 
-        (options.0, file, path_buf)
+        (options, file, path_buf)
     }
 }
 
-impl Write for SerdeFile {
+impl<FS: FileSystem> Write for SerdeFile<FS> {
     #[inline(always)]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.1.write(buf)
@@ -107,7 +364,10 @@ impl Write for SerdeFile {
     }
 }
 
-impl<'a> Write for &'a SerdeFile {
+impl<'a, FS: FileSystem> Write for &'a SerdeFile<FS>
+where
+    &'a FS::File: Write,
+{
     #[inline(always)]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         (&self.1).write(buf)
@@ -119,36 +379,42 @@ impl<'a> Write for &'a SerdeFile {
     }
 }
 
-impl Read for SerdeFile {
+impl<FS: FileSystem> Read for SerdeFile<FS> {
     #[inline(always)]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.1.read(buf)
     }
 }
 
-impl<'a> Read for &'a SerdeFile {
+impl<'a, FS: FileSystem> Read for &'a SerdeFile<FS>
+where
+    &'a FS::File: Read,
+{
     #[inline(always)]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         (&self.1).read(buf)
     }
 }
 
-impl Seek for SerdeFile {
+impl<FS: FileSystem> Seek for SerdeFile<FS> {
     #[inline(always)]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         self.1.seek(pos)
     }
 }
 
-impl<'a> Seek for &'a SerdeFile {
+impl<'a, FS: FileSystem> Seek for &'a SerdeFile<FS>
+where
+    &'a FS::File: Seek,
+{
     #[inline(always)]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         (&self.1).seek(pos)
     }
 }
 
-impl Deref for SerdeFile {
-    type Target = File;
+impl<FS: FileSystem> Deref for SerdeFile<FS> {
+    type Target = FS::File;
 
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
@@ -156,36 +422,135 @@ impl Deref for SerdeFile {
     }
 }
 
-impl DerefMut for SerdeFile {
+impl<FS: FileSystem> DerefMut for SerdeFile<FS> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.1
     }
 }
 
-impl Serialize for SerdeFile {
+/// Seeks `file` to `position`, clamped to the file's current length in case it shrank since
+/// `position` was recorded.
+fn seek_to_saved_position<F: Seek>(file: &mut F, position: u64) -> io::Result<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+
+    file.seek(SeekFrom::Start(position.min(len)))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "scm-rights")]
+impl<FS: FileSystem> Serialize for SerdeFile<FS>
+where
+    FS::OpenOptions: Serialize,
+    for<'r> &'r FS::File: Seek,
+    FS::File: std::os::unix::io::AsRawFd,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use std::os::unix::io::AsRawFd;
+
+        let position = (&self.1).stream_position().map_err(ser::Error::custom)?;
+
+        // Only embed a descriptor index when serializing for `send`; a plain serialize call
+        // (bincode, serde_json, ...) must not touch the fd side-channel, or a later plain
+        // deserialize could claim a descriptor a live `SerdeFile` still owns.
+        if scm_rights::is_active() {
+            let index = scm_rights::push_fd(self.1.as_raw_fd());
+            (&self.0, &self.2, position, Some(index)).serialize(serializer)
+        } else {
+            (&self.0, &self.2, position, None::<u32>).serialize(serializer)
+        }
+    }
+}
+
+#[cfg(not(feature = "scm-rights"))]
+impl<FS: FileSystem> Serialize for SerdeFile<FS>
+where
+    FS::OpenOptions: Serialize,
+    for<'r> &'r FS::File: Seek,
+{
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        (&self.0, &self.2).serialize(serializer)
+        let position = (&self.1).stream_position().map_err(ser::Error::custom)?;
+
+        (&self.0, &self.2, position).serialize(serializer)
     }
 }
 
-impl<'a> Deserialize<'a> for SerdeFile
+#[cfg(feature = "scm-rights")]
+impl<'a, FS: FileSystem> Deserialize<'a> for SerdeFile<FS>
 where
-    Self: 'a,
+    FS::OpenOptions: Deserialize<'a>,
+    FS::File: std::os::unix::io::FromRawFd,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'a>,
     {
-        let (options, path_buf) = <(BytesSer<OpenOptions>, PathBuf)>::deserialize(deserializer)?;
+        use std::os::unix::io::FromRawFd;
+
+        let (options, path_buf, position, index) =
+            <(FS::OpenOptions, PathBuf, u64, Option<u32>)>::deserialize(deserializer)?;
+
+        // Mirrors the `Serialize` impl: only consult the fd side-channel when the index was
+        // actually embedded, i.e. the matching serialize went through `send`.
+        if let Some(fd) = index
+            .filter(|_| scm_rights::is_active())
+            .and_then(scm_rights::take_fd)
+        {
+            return Ok(SerdeFile(options, unsafe { FS::File::from_raw_fd(fd) }, path_buf));
+        }
 
-        SerdeFile::open(&options.0, &path_buf).map_err(|e| {
+        let mut file = SerdeFile::<FS>::open(&options, &path_buf).map_err(|e| {
             de::Error::custom(format_args!(
                 "error with opening {}: {}",
                 path_buf.display(),
                 e
             ))
-        })
+        })?;
+
+        seek_to_saved_position(&mut file.1, position).map_err(|e| {
+            de::Error::custom(format_args!(
+                "error seeking {} to offset {}: {}",
+                path_buf.display(),
+                position,
+                e
+            ))
+        })?;
+
+        Ok(file)
+    }
+}
+
+#[cfg(not(feature = "scm-rights"))]
+impl<'a, FS: FileSystem> Deserialize<'a> for SerdeFile<FS>
+where
+    FS::OpenOptions: Deserialize<'a>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        let (options, path_buf, position) =
+            <(FS::OpenOptions, PathBuf, u64)>::deserialize(deserializer)?;
+
+        let mut file = SerdeFile::<FS>::open(&options, &path_buf).map_err(|e| {
+            de::Error::custom(format_args!(
+                "error with opening {}: {}",
+                path_buf.display(),
+                e
+            ))
+        })?;
+
+        seek_to_saved_position(&mut file.1, position).map_err(|e| {
+            de::Error::custom(format_args!(
+                "error seeking {} to offset {}: {}",
+                path_buf.display(),
+                position,
+                e
+            ))
+        })?;
+
+        Ok(file)
     }
 }
 
@@ -206,19 +571,240 @@ mod test {
             std::fs::remove_file(x).unwrap_or_default()
         }
 
-        let mut f = SerdeFile::open(
-            OpenOptions::new().read(true).write(true).create(true),
+        let mut f: SerdeFile = SerdeFile::open(
+            OpenMode::new().read(true).write(true).create(true),
             FILE_PATH,
         )
         .unwrap();
 
         write!(f, "{}", S1).unwrap();
 
+        let position = f.stream_position().unwrap();
+
         let fbytes = serialize(&f).unwrap();
 
         drop(f);
         let mut f2: SerdeFile = deserialize(&fbytes).unwrap();
 
+        assert_eq!(position, f2.stream_position().unwrap());
+
+        f2.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut vec = Vec::new();
+
+        f2.read_to_end(&mut vec).unwrap();
+
+        assert_eq!(S1.as_bytes(), &vec);
+    }
+
+    const FILE_PATH_JSON: &str = r".\a_json.txt";
+
+    /// `OpenMode`/`SerdeFile` must round-trip through human-readable formats as well as
+    /// bincode, since that's the whole point of mirroring `OpenOptions` as named fields instead
+    /// of transmuting its raw bytes.
+    #[test]
+    fn json_roundtrip() {
+        scopeguard::defer! {
+            let y = FILE_PATH_JSON;
+            let x = unsafe { (&y as *const &str).read_volatile() };
+
+            std::fs::remove_file(x).unwrap_or_default()
+        }
+
+        let mut f: SerdeFile = SerdeFile::open(
+            OpenMode::new().read(true).write(true).create(true),
+            FILE_PATH_JSON,
+        )
+        .unwrap();
+
+        write!(f, "{}", S1).unwrap();
+
+        let position = f.stream_position().unwrap();
+
+        let json = serde_json::to_string(&f).unwrap();
+
+        drop(f);
+        let mut f2: SerdeFile = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(position, f2.stream_position().unwrap());
+
+        f2.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut vec = Vec::new();
+
+        f2.read_to_end(&mut vec).unwrap();
+
+        assert_eq!(S1.as_bytes(), &vec);
+    }
+
+    const FILE_PATH_SHRUNK: &str = r".\a_shrunk.txt";
+
+    /// If the file shrinks between serializing and deserializing, `seek_to_saved_position` must
+    /// clamp the restored offset to the new length instead of seeking past the end of the file.
+    #[test]
+    fn seek_clamps_to_shrunk_length() {
+        scopeguard::defer! {
+            let y = FILE_PATH_SHRUNK;
+            let x = unsafe { (&y as *const &str).read_volatile() };
+
+            std::fs::remove_file(x).unwrap_or_default()
+        }
+
+        let mut f: SerdeFile = SerdeFile::open(
+            OpenMode::new().read(true).write(true).create(true),
+            FILE_PATH_SHRUNK,
+        )
+        .unwrap();
+
+        write!(f, "{}", S1).unwrap();
+
+        let position = f.stream_position().unwrap();
+        assert_eq!(position, S1.len() as u64);
+
+        let fbytes = serialize(&f).unwrap();
+
+        f.set_len(1).unwrap();
+        drop(f);
+
+        let mut f2: SerdeFile = deserialize(&fbytes).unwrap();
+
+        assert_eq!(1, f2.stream_position().unwrap());
+    }
+
+    /// A minimal in-memory [`FileSystem`], standing in for a virtual/sandboxed backend: "files"
+    /// are byte buffers shared by path in a process-wide table, so a `deserialize`'s re-open sees
+    /// whatever a prior `serialize` observed.
+    ///
+    /// Not exercised under `scm-rights`: that feature's `Serialize`/`Deserialize` impls require
+    /// `FS::File: AsRawFd`/`FromRawFd`, which an in-memory handle has no real descriptor to back.
+    #[cfg(not(feature = "scm-rights"))]
+    mod memfs {
+        use super::*;
+        use std::{
+            cell::Cell,
+            collections::HashMap,
+            sync::{Arc, Mutex, OnceLock},
+        };
+
+        type FileTable = HashMap<PathBuf, Arc<Mutex<Vec<u8>>>>;
+
+        fn table() -> &'static Mutex<FileTable> {
+            static TABLE: OnceLock<Mutex<FileTable>> = OnceLock::new();
+            TABLE.get_or_init(|| Mutex::new(HashMap::new()))
+        }
+
+        #[derive(Debug, Clone, Copy, Default)]
+        pub(super) struct MemFs;
+
+        pub(super) struct MemFile {
+            data: Arc<Mutex<Vec<u8>>>,
+            // `Cell` so `&MemFile` can seek/read too, mirroring `File`'s impls for `&File`.
+            pos: Cell<u64>,
+        }
+
+        impl Read for &MemFile {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let data = self.data.lock().unwrap();
+                let start = (self.pos.get() as usize).min(data.len());
+                let n = (&data[start..]).read(buf)?;
+                self.pos.set(self.pos.get() + n as u64);
+                Ok(n)
+            }
+        }
+
+        impl Read for MemFile {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                (&*self).read(buf)
+            }
+        }
+
+        impl Write for MemFile {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let mut data = self.data.lock().unwrap();
+                let start = self.pos.get() as usize;
+
+                if start + buf.len() > data.len() {
+                    data.resize(start + buf.len(), 0);
+                }
+
+                data[start..start + buf.len()].copy_from_slice(buf);
+                self.pos.set(self.pos.get() + buf.len() as u64);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl Seek for &MemFile {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                let len = self.data.lock().unwrap().len() as u64;
+
+                let new_pos = match pos {
+                    SeekFrom::Start(p) => p,
+                    SeekFrom::End(p) => (len as i64 + p).max(0) as u64,
+                    SeekFrom::Current(p) => (self.pos.get() as i64 + p).max(0) as u64,
+                };
+
+                self.pos.set(new_pos);
+                Ok(new_pos)
+            }
+        }
+
+        impl Seek for MemFile {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                (&*self).seek(pos)
+            }
+        }
+
+        impl FileSystem for MemFs {
+            type File = MemFile;
+            type OpenOptions = ();
+
+            fn open(_options: &(), path: &Path) -> io::Result<MemFile> {
+                let data = table()
+                    .lock()
+                    .unwrap()
+                    .entry(path.to_path_buf())
+                    .or_insert_with(|| Arc::new(Mutex::new(Vec::new())))
+                    .clone();
+
+                Ok(MemFile {
+                    data,
+                    pos: Cell::new(0),
+                })
+            }
+
+            fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+                Ok(path.to_path_buf())
+            }
+        }
+    }
+
+    /// `SerdeFile` only needs `FileSystem::open`/`canonicalize` and its `File`/`OpenOptions`
+    /// associated types to hold up its end of the bargain, so it round-trips against a
+    /// non-`StdFs` backend the same way it does against a plain [`File`].
+    #[cfg(not(feature = "scm-rights"))]
+    #[test]
+    fn roundtrips_against_in_memory_filesystem() {
+        let path = PathBuf::from("/virtual/a.txt");
+
+        let mut f: SerdeFile<memfs::MemFs> = SerdeFile::open(&(), &path).unwrap();
+
+        write!(f, "{}", S1).unwrap();
+
+        let position = f.stream_position().unwrap();
+
+        let fbytes = serialize(&f).unwrap();
+
+        drop(f);
+        let mut f2: SerdeFile<memfs::MemFs> = deserialize(&fbytes).unwrap();
+
+        assert_eq!(position, f2.stream_position().unwrap());
+
+        f2.seek(SeekFrom::Start(0)).unwrap();
+
         let mut vec = Vec::new();
 
         f2.read_to_end(&mut vec).unwrap();
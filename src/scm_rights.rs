@@ -0,0 +1,343 @@
+//! File-descriptor passing for [`SerdeFile`] over Unix domain sockets.
+//!
+//! Re-opening a path on deserialize (the default behavior of [`SerdeFile`]'s [`Deserialize`]
+//! impl) fails for unlinked temporary files, loses the original file offset, and races if the
+//! file changed since it was serialized. This module transfers the live descriptor itself over
+//! a [`UnixStream`] using `SCM_RIGHTS` ancillary data, the same side-channel technique IPC
+//! crates use to hand descriptors between processes.
+//!
+//! [`Deserialize`]: serde::Deserialize
+
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Read},
+    mem,
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+};
+
+/// Messages larger than this are rejected; kept fixed-size so a single `recvmsg` call can size
+/// its buffer upfront.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+thread_local! {
+    /// Descriptors a [`SerdeFile`]'s `Serialize` impl has pushed while being packed for [`send`],
+    /// or descriptors [`recv`] has made available for a `Deserialize` impl to pop. A slot is
+    /// `None` once its descriptor has been taken, so that leftover `Some`s after decoding reveal
+    /// indices that were never consumed.
+    ///
+    /// [`SerdeFile`]: crate::SerdeFile
+    static FDS: RefCell<Vec<Option<RawFd>>> = const { RefCell::new(Vec::new()) };
+
+    /// Whether the current thread is inside a [`send`]/[`recv`] call; see [`is_active`].
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the current thread is inside a [`send`]/[`recv`] call. `SerdeFile`'s `Serialize`/
+/// `Deserialize` impls check this to confine the fd side-channel (`push_fd`/`take_fd`) to those
+/// entry points, so a plain `bincode::serialize`/`deserialize` call never touches `FDS` and can't
+/// hand out a descriptor still owned by a live `SerdeFile`.
+pub(crate) fn is_active() -> bool {
+    ACTIVE.with(Cell::get)
+}
+
+/// RAII guard that marks the fd side-channel active for its lifetime and restores the previous
+/// state on drop, so nested `send`/`recv` calls (if any) don't clobber each other's flag.
+struct ActiveGuard(bool);
+
+impl ActiveGuard {
+    fn enter() -> Self {
+        ACTIVE.with(|active| {
+            let previous = active.replace(true);
+            ActiveGuard(previous)
+        })
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| active.set(self.0));
+    }
+}
+
+/// Pushes `fd` onto the thread-local descriptor stack and returns its index, for
+/// [`SerdeFile`](crate::SerdeFile)'s `Serialize` impl to embed in the data stream in place of
+/// the raw descriptor.
+pub(crate) fn push_fd(fd: RawFd) -> u32 {
+    FDS.with(|fds| {
+        let mut fds = fds.borrow_mut();
+        fds.push(Some(fd));
+        (fds.len() - 1) as u32
+    })
+}
+
+/// Pops the descriptor previously registered at `index` by [`recv`], for
+/// [`SerdeFile`](crate::SerdeFile)'s `Deserialize` impl to reconstruct a `File` from.
+pub(crate) fn take_fd(index: u32) -> Option<RawFd> {
+    FDS.with(|fds| fds.borrow_mut().get_mut(index as usize)?.take())
+}
+
+/// Serializes `value` into a byte buffer while collecting, via the thread-local descriptor
+/// stack, any file descriptors its `Serialize` impl pushed onto it, then sends both the bytes
+/// and the descriptors (packed as a single `SCM_RIGHTS` control message) over `socket`.
+pub fn send<T: serde::Serialize>(socket: &UnixStream, value: &T) -> io::Result<()> {
+    let _active = ActiveGuard::enter();
+
+    FDS.with(|fds| fds.borrow_mut().clear());
+
+    // `bincode::serialize` precomputes the output size with a throwaway `Serialize` pass before
+    // the real one, which would call `push_fd` twice for a `SerdeFile`. `serialize_into` writes
+    // straight into `bytes` in a single pass, so the side effect fires exactly once.
+    let mut bytes = Vec::new();
+    bincode::serialize_into(&mut bytes, value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if bytes.len() > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "serialized message exceeds the scm-rights maximum message length",
+        ));
+    }
+
+    let fds: Vec<RawFd> = FDS.with(|fds| fds.borrow_mut().drain(..).flatten().collect());
+
+    send_with_fds(socket, &bytes, &fds)
+}
+
+/// Receives a message sent by [`send`] from `socket`, makes its descriptors available to the
+/// thread-local descriptor stack, then deserializes and returns `T`. Fails if any descriptor
+/// received alongside the message is left unclaimed once decoding is done, since that means the
+/// fd count disagreed with the indices referenced while decoding.
+pub fn recv<T: serde::de::DeserializeOwned>(socket: &UnixStream) -> io::Result<T> {
+    let _active = ActiveGuard::enter();
+
+    let (bytes, fds) = recv_with_fds(socket)?;
+
+    FDS.with(|cell| *cell.borrow_mut() = fds.into_iter().map(Some).collect());
+
+    let value = bincode::deserialize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+
+    let leftover = FDS.with(|cell| cell.borrow_mut().drain(..).flatten().collect::<Vec<_>>());
+
+    for fd in &leftover {
+        unsafe { libc::close(*fd) };
+    }
+
+    let value = value?;
+
+    if !leftover.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "received fd count does not match the indices referenced while decoding",
+        ));
+    }
+
+    Ok(value)
+}
+
+fn send_with_fds(socket: &UnixStream, bytes: &[u8], fds: &[RawFd]) -> io::Result<()> {
+    let len = (bytes.len() as u32).to_le_bytes();
+
+    let mut iov = [
+        libc::iovec {
+            iov_base: len.as_ptr() as *mut _,
+            iov_len: len.len(),
+        },
+        libc::iovec {
+            iov_base: bytes.as_ptr() as *mut _,
+            iov_len: bytes.len(),
+        },
+    ];
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = iov.as_mut_ptr();
+    msg.msg_iovlen = iov.len() as _;
+
+    let mut cmsg_buf =
+        vec![0u8; unsafe { libc::CMSG_SPACE(mem::size_of_val(fds) as u32) } as usize];
+
+    if !fds.is_empty() {
+        msg.msg_control = cmsg_buf.as_mut_ptr() as *mut _;
+        msg.msg_controllen = cmsg_buf.len() as _;
+
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of_val(fds) as u32) as _;
+
+            std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg) as *mut RawFd, fds.len());
+        }
+    }
+
+    let sent = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn recv_with_fds(socket: &UnixStream) -> io::Result<(Vec<u8>, Vec<RawFd>)> {
+    let mut len_buf = [0u8; mem::size_of::<u32>()];
+    let mut payload_buf = vec![0u8; MAX_MESSAGE_LEN];
+
+    let mut iov = [
+        libc::iovec {
+            iov_base: len_buf.as_mut_ptr() as *mut _,
+            iov_len: len_buf.len(),
+        },
+        libc::iovec {
+            iov_base: payload_buf.as_mut_ptr() as *mut _,
+            iov_len: payload_buf.len(),
+        },
+    ];
+
+    const MAX_FDS: usize = 32;
+
+    let mut cmsg_buf =
+        vec![0u8; unsafe { libc::CMSG_SPACE((MAX_FDS * mem::size_of::<RawFd>()) as u32) } as usize];
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = iov.as_mut_ptr();
+    msg.msg_iovlen = iov.len() as _;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut _;
+    msg.msg_controllen = cmsg_buf.len() as _;
+
+    let received = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut fds = Vec::new();
+
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize) / mem::size_of::<RawFd>();
+
+                for i in 0..count {
+                    fds.push(*data.add(i));
+                }
+            }
+
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    // Parse whatever fds the kernel did deliver before bailing out on truncation, so they get
+    // closed instead of leaking as live descriptors in this process.
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        for fd in &fds {
+            unsafe { libc::close(*fd) };
+        }
+
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "scm-rights ancillary data was truncated (more than the maximum descriptors arrived)",
+        ));
+    }
+
+    if (received as usize) < len_buf.len() {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short scm-rights message"));
+    }
+
+    let len = u32::from_le_bytes(len_buf) as usize;
+    let mut received_payload = (received as usize) - len_buf.len();
+    payload_buf.truncate(len);
+
+    // `UnixStream` is SOCK_STREAM, so a single `recvmsg` isn't guaranteed to have returned the
+    // whole framed payload; keep reading until `len` bytes have arrived.
+    let mut socket = socket;
+
+    while received_payload < len {
+        let n = socket.read(&mut payload_buf[received_payload..])?;
+
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before the full scm-rights message was received",
+            ));
+        }
+
+        received_payload += n;
+    }
+
+    Ok((payload_buf, fds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{OpenMode, SerdeFile};
+    use std::io::{Seek, SeekFrom, Write};
+
+    /// `send`/`recv` must hand the live descriptor itself across the socket rather than
+    /// re-opening the path, preserving the file's offset. Unlinking the path before sending
+    /// proves it: a path-based fallback would hit `ENOENT` on the receiving end.
+    #[test]
+    fn send_recv_preserves_unlinked_file() {
+        let path = std::env::temp_dir().join(format!(
+            "serde_file_scm_rights_test_{}_{}.txt",
+            std::process::id(),
+            line!()
+        ));
+
+        let mut file: SerdeFile =
+            SerdeFile::open(OpenMode::new().read(true).write(true).create(true), &path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        write!(file, "hello world").unwrap();
+        file.seek(SeekFrom::Start(6)).unwrap();
+        let position = file.stream_position().unwrap();
+
+        let (tx, rx) = UnixStream::pair().unwrap();
+
+        send(&tx, &file).unwrap();
+        drop(file);
+
+        let mut received: SerdeFile = recv(&rx).unwrap();
+
+        assert_eq!(position, received.stream_position().unwrap());
+
+        let mut buf = Vec::new();
+        received.read_to_end(&mut buf).unwrap();
+        assert_eq!(b"world", &buf[..]);
+    }
+
+    /// A value whose `Serialize` impl pushes a descriptor onto the fd side-channel without
+    /// embedding its index anywhere in the encoded bytes, standing in for a `SerdeFile` whose
+    /// index got lost or duplicated.
+    struct ExtraFd(RawFd);
+
+    impl serde::Serialize for ExtraFd {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            push_fd(self.0);
+            serializer.serialize_unit()
+        }
+    }
+
+    /// If the sender attaches a descriptor that the serialized payload never references by
+    /// index, `recv` must reject it instead of silently dropping it (which would leak the fd)
+    /// or silently accepting the disagreement between fd count and referenced indices.
+    #[test]
+    fn unclaimed_fd_is_rejected() {
+        let (tx, rx) = UnixStream::pair().unwrap();
+
+        let dummy = std::fs::File::open("/dev/null").unwrap();
+
+        send(&tx, &ExtraFd(dummy.as_raw_fd())).unwrap();
+
+        let result: io::Result<()> = recv(&rx);
+
+        assert!(result.is_err());
+    }
+}